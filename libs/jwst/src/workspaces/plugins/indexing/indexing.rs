@@ -1,72 +1,556 @@
-use super::{Content, PluginImpl};
+use super::{Content, PluginImpl, PluginRegister};
 use lib0::any::Any;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::rc::Rc;
 use std::sync::{atomic::AtomicU32, Arc};
-use tantivy::{collector::TopDocs, query::QueryParser, schema::*, Index, ReloadPolicy};
+use tantivy::{
+    collector::{Count, TopDocs},
+    directory::MmapDirectory,
+    query::{BooleanQuery, Occur, Query, QueryParser, TermQuery},
+    schema::*,
+    snippet::SnippetGenerator,
+    tokenizer::{NgramTokenizer, TextAnalyzer, WhitespaceTokenizer},
+    DocId, Index, ReloadPolicy, Score, SegmentReader, Term,
+};
 use utoipa::ToSchema;
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct SearchResult {
     pub block_id: String,
     pub score: f32,
+    /// An HTML-tagged excerpt of the `body` field around the matched terms,
+    /// present when the originating [`SearchQuery`] asked for `highlight`.
+    pub snippet: Option<String>,
 }
 
 /// Returned from [`Workspace::search`]
 ///
 /// [`Workspace::search`]: crate::Workspace::search
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
-pub struct SearchResults(Vec<SearchResult>);
+pub struct SearchResults {
+    pub items: Vec<SearchResult>,
+    /// Total number of matching documents, not just the ones in this page.
+    pub total: usize,
+}
+
+/// A full-text search request, shaped after MeiliSearch's search request body.
+#[derive(Debug, Clone)]
+pub struct SearchQuery {
+    pub query: String,
+    pub limit: usize,
+    pub offset: usize,
+    /// Restricts which fields are searched; `None` searches `title` and `body`.
+    pub fields_to_search: Option<Vec<String>>,
+    pub highlight: bool,
+    pub ranking: SearchRanking,
+    /// Scopes results to blocks matching every given facet, e.g. a given
+    /// `flavor` and/or `attached` state.
+    pub filter: Option<BlockFilter>,
+}
+
+impl SearchQuery {
+    pub fn new<S: Into<String>>(query: S) -> Self {
+        Self {
+            query: query.into(),
+            limit: 10,
+            offset: 0,
+            fields_to_search: None,
+            highlight: false,
+            ranking: SearchRanking::default(),
+            filter: None,
+        }
+    }
+
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    pub fn with_offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    pub fn with_fields<S: Into<String>>(mut self, fields: Vec<S>) -> Self {
+        self.fields_to_search = Some(fields.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn with_highlight(mut self, highlight: bool) -> Self {
+        self.highlight = highlight;
+        self
+    }
+
+    pub fn with_ranking(mut self, ranking: SearchRanking) -> Self {
+        self.ranking = ranking;
+        self
+    }
+
+    pub fn with_filter(mut self, filter: BlockFilter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+}
+
+/// A facet filter intersected with the free-text query as a `BooleanQuery`,
+/// e.g. `flavor = "affine:text" AND attached = true`, so clients can scope
+/// search to a block type or exclude detached blocks without post-filtering
+/// the result set.
+///
+/// Note `attached` is derived purely from whether some other block currently
+/// lists this one as a child: a top-level/root block looks identical to a
+/// never-attached orphan by this measure, since neither has a parent.
+///
+/// A block that *transitions* from attached to detached is removed from the
+/// index entirely rather than re-indexed with `attached = false` (see
+/// `on_update`), so `attached = false` only ever describes a block that was
+/// never attached to begin with.
+#[derive(Debug, Clone, Default)]
+pub struct BlockFilter {
+    pub flavor: Option<String>,
+    pub attached: Option<bool>,
+}
+
+impl BlockFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_flavor<S: Into<String>>(mut self, flavor: S) -> Self {
+        self.flavor = Some(flavor.into());
+        self
+    }
+
+    pub fn with_attached(mut self, attached: bool) -> Self {
+        self.attached = Some(attached);
+        self
+    }
+}
+
+/// The state a single block was last *actually written to the index* with, used
+/// to diff against the current workspace snapshot so `on_update` only touches
+/// documents that actually changed. Deliberately distinct from "last observed
+/// workspace state": a block that was purged on detach must not reappear in
+/// `indexed_blocks` just because its content keeps changing while it stays
+/// detached (see `IndexingPluginImpl::purged`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct IndexedBlock {
+    title: Option<String>,
+    body: Option<String>,
+    /// `Some(parent_id)` while the block is attached somewhere in the block tree,
+    /// `None` once it has been detached (or was never attached to begin with).
+    parent: Option<String>,
+    /// Distance from the root of the block tree (0 for a top-level block), stored
+    /// as a fast field so [`SearchRanking::BoostedWithDepth`] can read it back
+    /// per-segment without touching the document store.
+    depth: u64,
+    /// The block's flavor (e.g. `affine:text`), stored as a facet so a
+    /// [`BlockFilter`] can scope search to a single block type.
+    flavor: String,
+}
 
 pub struct IndexingPluginImpl {
-    // /// `true` if the text search has not yet populated the Tantivy index
-    // /// `false` if there should only be incremental changes necessary to the blocks.
-    // first_index: bool,
     pub(super) queue_reindex: Arc<AtomicU32>,
     pub(super) schema: Schema,
     pub(super) index: Rc<Index>,
     pub(super) query_parser: QueryParser,
+    // re-applied whenever `search_with` builds its own field-restricted QueryParser
+    title_boost: f32,
+    body_boost: f32,
+    // last-indexed snapshot, keyed by block_id, so we can diff rather than re-add everything
+    indexed_blocks: HashMap<String, IndexedBlock>,
+    // ids that were hard-deleted from the index because they detached from the block
+    // tree, and haven't been re-attached since -- kept separately from `indexed_blocks`
+    // so a content edit to a still-detached block doesn't resurrect it (it's simply
+    // absent from `indexed_blocks`, which on its own looks identical to "never indexed").
+    purged: std::collections::HashSet<String>,
     // need to keep so it gets dropped with this plugin
     pub(super) _update_sub: yrs::Subscription<yrs::UpdateEvent>,
 }
 
+/// How a [`SearchQuery`] ranks its matches.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum SearchRanking {
+    /// Plain BM25, with the field boosts configured at registration applied.
+    #[default]
+    Bm25,
+    /// BM25 plus a small bonus for shallower blocks: the final score is
+    /// `bm25_score * boost + 1 / (1 + child_depth)`, computed by a [`TopDocs`]
+    /// score tweaker that reads the `child_depth` fast field per segment.
+    BoostedWithDepth,
+}
+
+/// Where an [`IndexingPluginImpl`]'s Tantivy index lives.
+enum IndexLocation {
+    /// Rebuilt from scratch every time the process starts.
+    Ram,
+    /// Reused across restarts: segments are read back from `directory` on open.
+    Persisted(PathBuf),
+}
+
+/// Which Tantivy tokenizer a `title`/`body` field is indexed and queried with.
+/// Registration picks one per field so mixed-language workspaces can be tuned
+/// instead of relying on whatever Tantivy's default happens to do.
+#[derive(Debug, Clone, Copy)]
+pub enum FieldTokenizer {
+    /// Tantivy's built-in English stemmer; the right default for Latin-script text.
+    EnStem,
+    /// Overlapping 1-2 character n-grams (e.g. "技术学校" -> "技","技术","术",...),
+    /// since CJK text has no whitespace word boundaries for a stemmer to use.
+    Cjk,
+    /// Splits purely on whitespace, with no stemming or stopword removal.
+    Whitespace,
+}
+
+impl FieldTokenizer {
+    fn name(self) -> &'static str {
+        match self {
+            Self::EnStem => "en_stem",
+            Self::Cjk => "cjk",
+            Self::Whitespace => "whitespace",
+        }
+    }
+}
+
+impl Default for FieldTokenizer {
+    fn default() -> Self {
+        Self::EnStem
+    }
+}
+
+/// Registers the [`IndexingPluginImpl`] plugin, either backed by a RAM index that
+/// is rebuilt on every start, or by a directory of Tantivy segments that is
+/// reused across restarts.
+pub struct IndexingPluginRegister {
+    location: IndexLocation,
+    title_tokenizer: FieldTokenizer,
+    body_tokenizer: FieldTokenizer,
+    title_boost: f32,
+    body_boost: f32,
+}
+
+impl IndexingPluginRegister {
+    /// Index lives entirely in memory and is rebuilt from the workspace on every
+    /// process start.
+    pub fn ram() -> Self {
+        Self {
+            location: IndexLocation::Ram,
+            title_tokenizer: FieldTokenizer::default(),
+            body_tokenizer: FieldTokenizer::default(),
+            title_boost: 1.0,
+            body_boost: 1.0,
+        }
+    }
+
+    /// Index is persisted under `directory` (e.g. `.../search-index/<workspace_id>`)
+    /// and reused across restarts: an existing index only receives incremental
+    /// updates, an empty/missing one gets a one-time full build.
+    pub fn persisted<P: Into<PathBuf>>(directory: P) -> Self {
+        Self {
+            location: IndexLocation::Persisted(directory.into()),
+            title_tokenizer: FieldTokenizer::default(),
+            body_tokenizer: FieldTokenizer::default(),
+            title_boost: 1.0,
+            body_boost: 1.0,
+        }
+    }
+
+    /// Overrides the tokenizer used for the `title`/`body` fields, e.g.
+    /// [`FieldTokenizer::Cjk`] for a workspace that is mostly Chinese/Japanese/Korean.
+    pub fn with_tokenizers(mut self, title: FieldTokenizer, body: FieldTokenizer) -> Self {
+        self.title_tokenizer = title;
+        self.body_tokenizer = body;
+        self
+    }
+
+    /// Weights title matches against body matches, e.g. `3.0, 1.0` so a title hit
+    /// outranks a body hit on otherwise-equal BM25 terms.
+    pub fn with_field_boosts(mut self, title_boost: f32, body_boost: f32) -> Self {
+        self.title_boost = title_boost;
+        self.body_boost = body_boost;
+        self
+    }
+
+    /// Registers the tokenizers this crate ships (besides Tantivy's built-in ones)
+    /// on `index`, so both indexing and query-time parsing agree on segmentation.
+    fn register_tokenizers(index: &Index) -> Result<(), Box<dyn std::error::Error>> {
+        index
+            .tokenizers()
+            .register("cjk", TextAnalyzer::from(NgramTokenizer::new(1, 2, false)?));
+        index
+            .tokenizers()
+            .register("whitespace", TextAnalyzer::from(WhitespaceTokenizer));
+        Ok(())
+    }
+
+    fn text_field_options(tokenizer: FieldTokenizer) -> TextOptions {
+        TextOptions::default().set_indexing_options(
+            TextFieldIndexing::default()
+                .set_tokenizer(tokenizer.name())
+                .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+        )
+    }
+}
+
+impl PluginRegister for IndexingPluginRegister {
+    type Plugin = IndexingPluginImpl;
+
+    fn setup(self, content: &mut Content) -> Result<Self::Plugin, Box<dyn std::error::Error>> {
+        let mut schema_builder = Schema::builder();
+        let block_id_field = schema_builder.add_text_field("block_id", STRING | STORED);
+        schema_builder.add_text_field("title", Self::text_field_options(self.title_tokenizer));
+        // stored so `SnippetGenerator` can pull the original text back out for highlighting
+        schema_builder.add_text_field(
+            "body",
+            Self::text_field_options(self.body_tokenizer).set_stored(),
+        );
+        schema_builder.add_u64_field("child_depth", FAST);
+        schema_builder.add_text_field("flavor", STRING | STORED);
+        schema_builder.add_bool_field("attached", INDEXED);
+        let schema = schema_builder.build();
+
+        let (index, first_index) = match self.location {
+            IndexLocation::Ram => (Index::create_in_ram(schema.clone()), true),
+            IndexLocation::Persisted(directory) => {
+                std::fs::create_dir_all(&directory)?;
+                let index =
+                    Index::open_or_create(MmapDirectory::open(&directory)?, schema.clone())?;
+                let is_empty = index.reader()?.searcher().num_docs() == 0;
+                (index, is_empty)
+            }
+        };
+        Self::register_tokenizers(&index)?;
+
+        let title_field = schema.get_field("title").unwrap();
+        let body_field = schema.get_field("body").unwrap();
+        // `QueryParser::for_index` resolves each field's tokenizer from the index's
+        // tokenizer manager, so registering "cjk"/"whitespace" above is enough to
+        // keep query-time segmentation in step with index-time segmentation.
+        let mut query_parser = QueryParser::for_index(&index, vec![title_field, body_field]);
+        query_parser.set_field_boost(title_field, self.title_boost);
+        query_parser.set_field_boost(body_field, self.body_boost);
+
+        // A fresh index (RAM, or a newly created on-disk one) needs a one-time full
+        // build; a populated on-disk index is trusted as-is and only receives
+        // incremental updates from here on.
+        let (queue_reindex, indexed_blocks) = if first_index {
+            (Arc::new(AtomicU32::new(1)), HashMap::new())
+        } else {
+            (
+                Arc::new(AtomicU32::new(0)),
+                Self::Plugin::snapshot_blocks(content),
+            )
+        };
+
+        let sub_queue_reindex = queue_reindex.clone();
+        let _update_sub = content.doc().observe_update_v1(move |_, _| {
+            sub_queue_reindex.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        })?;
+
+        Ok(IndexingPluginImpl {
+            queue_reindex,
+            schema,
+            index: Rc::new(index),
+            query_parser,
+            title_boost: self.title_boost,
+            body_boost: self.body_boost,
+            indexed_blocks,
+            purged: std::collections::HashSet::new(),
+            _update_sub,
+        })
+    }
+}
+
 impl IndexingPluginImpl {
+    /// Convenience wrapper around [`Self::search_with`] for a plain query string
+    /// with the default page size and no highlighting.
     pub fn search<S: AsRef<str>>(
         &self,
         query: S,
     ) -> Result<SearchResults, Box<dyn std::error::Error>> {
-        let mut items = Vec::new();
+        self.search_with(SearchQuery::new(query.as_ref()))
+    }
+
+    /// Re-applies the field boosts from registration to a freshly built
+    /// `QueryParser`, since `QueryParser::for_index` always starts unboosted.
+    fn apply_field_boosts(&self, parser: &mut QueryParser) {
+        let title_field = self.schema.get_field("title").unwrap();
+        let body_field = self.schema.get_field("body").unwrap();
+        parser.set_field_boost(title_field, self.title_boost);
+        parser.set_field_boost(body_field, self.body_boost);
+    }
+
+    /// Intersects `query` with the given [`BlockFilter`], if any, as a
+    /// `BooleanQuery` so facet clauses narrow results without post-filtering.
+    fn apply_filter(&self, query: Box<dyn Query>, filter: Option<&BlockFilter>) -> Box<dyn Query> {
+        let Some(filter) = filter else {
+            return query;
+        };
+
+        let mut clauses: Vec<(Occur, Box<dyn Query>)> = vec![(Occur::Must, query)];
+        if let Some(flavor) = &filter.flavor {
+            let flavor_field = self.schema.get_field("flavor").unwrap();
+            let term = Term::from_field_text(flavor_field, flavor);
+            clauses.push((
+                Occur::Must,
+                Box::new(TermQuery::new(term, IndexRecordOption::Basic)),
+            ));
+        }
+        if let Some(attached) = filter.attached {
+            let attached_field = self.schema.get_field("attached").unwrap();
+            let term = Term::from_field_bool(attached_field, attached);
+            clauses.push((
+                Occur::Must,
+                Box::new(TermQuery::new(term, IndexRecordOption::Basic)),
+            ));
+        }
 
+        if clauses.len() == 1 {
+            clauses.pop().unwrap().1
+        } else {
+            Box::new(BooleanQuery::new(clauses))
+        }
+    }
+
+    pub fn search_with(&self, q: SearchQuery) -> Result<SearchResults, Box<dyn std::error::Error>> {
         let reader = self
             .index
             .reader_builder()
             .reload_policy(ReloadPolicy::OnCommit)
             .try_into()?;
         let searcher = reader.searcher();
-        let query = self.query_parser.parse_query(query.as_ref())?;
-        let top_docs = searcher.search(&query, &TopDocs::with_limit(10))?;
-        // The actual documents still need to be retrieved from Tantivy’s store.
-        // Since the body field was not configured as stored, the document returned will only contain a title.
-
-        if !top_docs.is_empty() {
-            let block_id_field = self.schema.get_field("block_id").unwrap();
-
-            for (score, doc_address) in top_docs {
-                let retrieved_doc = searcher.doc(doc_address)?;
-                if let Some(Value::Str(id)) = retrieved_doc.get_first(block_id_field) {
-                    items.push(SearchResult {
-                        block_id: id.to_string(),
-                        score,
-                    });
-                } else {
-                    let to_json = self.schema.to_json(&retrieved_doc);
-                    eprintln!("Unexpected non-block doc in Tantivy result set: {to_json}");
-                }
+
+        let query_parser = match &q.fields_to_search {
+            Some(field_names) => {
+                let fields = field_names
+                    .iter()
+                    .filter_map(|name| self.schema.get_field(name))
+                    .collect::<Vec<_>>();
+                let mut parser = QueryParser::for_index(&self.index, fields);
+                self.apply_field_boosts(&mut parser);
+                parser
             }
+            None => self.query_parser.clone(),
+        };
+        let query = query_parser.parse_query(&q.query)?;
+        let query = self.apply_filter(query, q.filter.as_ref());
+
+        let block_id_field = self.schema.get_field("block_id").unwrap();
+        let body_field = self.schema.get_field("body").unwrap();
+        let snippet_generator = q
+            .highlight
+            .then(|| SnippetGenerator::create(&searcher, &*query, body_field))
+            .transpose()?;
+
+        // `TopDocs::with_limit` panics on 0, but a caller asking for zero rows (e.g.
+        // to read just `total`) is a perfectly valid `SearchQuery` -- skip the
+        // collector entirely rather than let that panic through.
+        if q.limit == 0 {
+            let total = searcher.search(&query, &Count)?;
+            return Ok(SearchResults {
+                items: Vec::new(),
+                total,
+            });
         }
 
-        Ok(SearchResults(items))
+        // limit+offset, then drop the first `offset` results, since Tantivy's
+        // collector only understands a window starting at zero
+        let limit = TopDocs::with_limit(q.limit + q.offset);
+        let (total, top_docs) = match q.ranking {
+            SearchRanking::Bm25 => searcher.search(&query, &(Count, limit))?,
+            SearchRanking::BoostedWithDepth => {
+                let child_depth_field = self.schema.get_field("child_depth").unwrap();
+                let tweaked = limit.tweak_score(move |segment_reader: &SegmentReader| {
+                    let child_depth_reader =
+                        segment_reader.fast_fields().u64(child_depth_field).unwrap();
+                    move |doc: DocId, original_score: Score| {
+                        let depth = child_depth_reader.get(doc);
+                        original_score + 1.0 / (1.0 + depth as f32)
+                    }
+                });
+                searcher.search(&query, &(Count, tweaked))?
+            }
+        };
+
+        let mut items = Vec::new();
+        for (score, doc_address) in top_docs.into_iter().skip(q.offset) {
+            let retrieved_doc = searcher.doc(doc_address)?;
+            if let Some(Value::Str(id)) = retrieved_doc.get_first(block_id_field) {
+                let snippet = snippet_generator
+                    .as_ref()
+                    .map(|generator| generator.snippet_from_doc(&retrieved_doc).to_html());
+                items.push(SearchResult {
+                    block_id: id.to_string(),
+                    score,
+                    snippet,
+                });
+            } else {
+                let to_json = self.schema.to_json(&retrieved_doc);
+                eprintln!("Unexpected non-block doc in Tantivy result set: {to_json}");
+            }
+        }
+
+        Ok(SearchResults { items, total })
+    }
+}
+
+impl IndexingPluginImpl {
+    /// Snapshots every block currently in the workspace, including which parent
+    /// (if any) it is attached under. Shared by `on_update`, which diffs against
+    /// the previous snapshot, and plugin setup, which seeds it for an
+    /// already-populated persisted index.
+    fn snapshot_blocks(ws: &Content) -> HashMap<String, IndexedBlock> {
+        // A block is only attached if some other block currently lists it as a
+        // child; everything else (detached, or never attached) maps to `None`.
+        let mut parent_of = HashMap::<String, String>::new();
+        for block in ws.block_iter() {
+            for child in block.children() {
+                parent_of.insert(child, block.id());
+            }
+        }
+
+        let mut blocks = HashMap::<String, IndexedBlock>::new();
+        for block in ws.block_iter() {
+            let title = block.content().get("title").and_then(|a| match a {
+                Any::String(str) => Some(str.to_string()),
+                _ => None,
+            });
+            let body = block.content().get("text").and_then(|a| match a {
+                Any::String(str) => Some(str.to_string()),
+                _ => None,
+            });
+            let parent = parent_of.get(&block.id()).cloned();
+            let depth = Self::depth_of(&block.id(), &parent_of);
+            blocks.insert(
+                block.id(),
+                IndexedBlock {
+                    title,
+                    body,
+                    parent,
+                    depth,
+                    flavor: block.flavor(),
+                },
+            );
+        }
+        blocks
+    }
+
+    /// Walks `parent_of` up from `id` to count how deep it sits in the block
+    /// tree. Guards against cycles so a malformed tree can't loop forever.
+    fn depth_of(id: &str, parent_of: &HashMap<String, String>) -> u64 {
+        let mut depth = 0;
+        let mut current = id;
+        let mut visited = std::collections::HashSet::new();
+        while let Some(parent) = parent_of.get(current) {
+            if !visited.insert(parent.as_str()) {
+                break;
+            }
+            depth += 1;
+            current = parent.as_str();
+        }
+        depth
     }
 }
 
@@ -74,31 +558,58 @@ impl PluginImpl for IndexingPluginImpl {
     fn on_update(&mut self, ws: &Content) -> Result<(), Box<dyn std::error::Error>> {
         let curr = self.queue_reindex.load(std::sync::atomic::Ordering::SeqCst);
         if curr > 0 {
-            let mut re_index_list = HashMap::<String, (Option<String>, Option<String>)>::new();
-            // TODO: reindex
-            for block in ws.block_iter() {
-                let title = block.content().get("title").map(ToOwned::to_owned);
-                let body = block.content().get("text").map(ToOwned::to_owned);
-                re_index_list.insert(
-                    block.id(),
-                    (
-                        title.and_then(|a| match a {
-                            Any::String(str) => Some(str.to_string()),
-                            _ => None,
-                        }),
-                        body.and_then(|a| match a {
-                            Any::String(str) => Some(str.to_string()),
-                            _ => None,
-                        }),
-                    ),
-                );
+            let current_blocks = Self::snapshot_blocks(ws);
+
+            // Diff against `indexed_blocks` (what's actually in the index right now),
+            // not just the previous workspace snapshot, so a block that was purged on
+            // detach doesn't get silently resurrected by a later content edit while
+            // it's still detached -- only re-attaching it does.
+            let mut dirty_blocks = HashMap::<String, Option<IndexedBlock>>::new();
+            let mut next_indexed_blocks = HashMap::<String, IndexedBlock>::new();
+            let mut next_purged = std::collections::HashSet::new();
+
+            for (id, block) in &current_blocks {
+                match self.indexed_blocks.get(id) {
+                    // already indexed and unchanged
+                    Some(indexed) if indexed == block => {
+                        next_indexed_blocks.insert(id.clone(), indexed.clone());
+                    }
+                    // transitioned from attached to detached: purge it, and remember
+                    // that it's purged so it stays that way while still detached.
+                    Some(indexed) if indexed.parent.is_some() && block.parent.is_none() => {
+                        dirty_blocks.insert(id.clone(), None);
+                        next_purged.insert(id.clone());
+                    }
+                    // already indexed, content changed, and still attached (or was
+                    // never attached to begin with): re-index with the new content.
+                    Some(_) => {
+                        dirty_blocks.insert(id.clone(), Some(block.clone()));
+                        next_indexed_blocks.insert(id.clone(), block.clone());
+                    }
+                    // not currently indexed, and still detached since we purged it:
+                    // stays purged no matter how its content changes in the meantime.
+                    None if block.parent.is_none() && self.purged.contains(id) => {
+                        next_purged.insert(id.clone());
+                    }
+                    // brand new, or re-attached after having been purged
+                    None => {
+                        dirty_blocks.insert(id.clone(), Some(block.clone()));
+                        next_indexed_blocks.insert(id.clone(), block.clone());
+                    }
+                }
+            }
+            // blocks that disappeared from the workspace entirely
+            for id in self.indexed_blocks.keys() {
+                if !current_blocks.contains_key(id) {
+                    dirty_blocks.insert(id.clone(), None);
+                }
             }
 
-            // dbg!((txn, upd));
-            // println!("got update event: {items}");
-            // just re-index stupidly
-            self.re_index_content(re_index_list)
+            self.re_index_content(dirty_blocks)
                 .map_err(|err| format!("Error during reindex: {err:?}"))?;
+
+            self.indexed_blocks = next_indexed_blocks;
+            self.purged = next_purged;
         }
 
         // reset back down now that the update was applied
@@ -110,37 +621,50 @@ impl PluginImpl for IndexingPluginImpl {
 }
 
 impl IndexingPluginImpl {
-    fn re_index_content<BlockIdTitleAndTextIter>(
+    /// Applies a set of dirty blocks to the index: `Some(block)` re-indexes it,
+    /// `None` removes it. Every id is deleted by its `block_id` term first, so an
+    /// edited block never leaves a stale duplicate behind, and a single commit
+    /// covers the whole batch so each delete+add pair lands atomically.
+    fn re_index_content<BlockIdAndContentIter>(
         &mut self,
-        blocks: BlockIdTitleAndTextIter,
+        blocks: BlockIdAndContentIter,
     ) -> Result<(), Box<dyn std::error::Error>>
     where
-        // TODO: use a structure with better names than tuples?
-        BlockIdTitleAndTextIter: IntoIterator<Item = (String, (Option<String>, Option<String>))>,
+        BlockIdAndContentIter: IntoIterator<Item = (String, Option<IndexedBlock>)>,
     {
         let block_id_field = self.schema.get_field("block_id").unwrap();
         let title_field = self.schema.get_field("title").unwrap();
         let body_field = self.schema.get_field("body").unwrap();
+        let child_depth_field = self.schema.get_field("child_depth").unwrap();
+        let flavor_field = self.schema.get_field("flavor").unwrap();
+        let attached_field = self.schema.get_field("attached").unwrap();
 
         let mut writer = self
             .index
             .writer(50_000_000)
             .map_err(|err| format!("Error creating writer: {err:?}"))?;
 
-        for (block_id, (block_title_opt, block_text_opt)) in blocks {
-            let mut block_doc = Document::new();
-            block_doc.add_text(block_id_field, block_id);
-            if let Some(block_title) = block_title_opt {
-                block_doc.add_text(title_field, block_title);
-            }
-            if let Some(block_text) = block_text_opt {
-                block_doc.add_text(body_field, block_text);
+        for (block_id, content) in blocks {
+            writer.delete_term(Term::from_field_text(block_id_field, &block_id));
+
+            if let Some(block) = content {
+                let mut block_doc = Document::new();
+                block_doc.add_text(block_id_field, block_id);
+                if let Some(block_title) = block.title {
+                    block_doc.add_text(title_field, block_title);
+                }
+                if let Some(block_text) = block.body {
+                    block_doc.add_text(body_field, block_text);
+                }
+                block_doc.add_u64(child_depth_field, block.depth);
+                block_doc.add_text(flavor_field, block.flavor);
+                block_doc.add_bool(attached_field, block.parent.is_some());
+                writer.add_document(block_doc)?;
             }
-            writer.add_document(block_doc)?;
         }
 
         // If .commit() returns correctly, then all of the documents that have been added
-        // are guaranteed to be persistently indexed.
+        // (and removed) are guaranteed to be persistently indexed.
         writer.commit()?;
 
         Ok(())
@@ -158,7 +682,7 @@ mod test {
     macro_rules! expect_result_ids {
         ($search_results:ident, $id_str_array:expr) => {
             let mut sorted_ids = $search_results
-                .0
+                .items
                 .iter()
                 .map(|i| &i.block_id)
                 .collect::<Vec<_>>();
@@ -187,8 +711,17 @@ mod test {
         let mut workspace = {
             let workspace = Workspace::from_doc(Default::default(), "wk-load");
             // even though the plugin is added by default,
-            super::super::super::insert_plugin(workspace, IndexingPluginRegister::ram())
-                .expect("failed to insert plugin")
+            // this workspace mixes English and Chinese content, and `en_stem`'s
+            // `SimpleTokenizer` treats a whole CJK clause as a single token
+            // (`char::is_alphanumeric` is true for CJK ideographs), so exact-match
+            // term queries on a CJK substring never hit -- use the n-gram tokenizer
+            // for both fields instead.
+            super::super::super::insert_plugin(
+                workspace,
+                IndexingPluginRegister::ram()
+                    .with_tokenizers(FieldTokenizer::Cjk, FieldTokenizer::Cjk),
+            )
+            .expect("failed to insert plugin")
         };
 
         workspace.with_trx(|mut t| {
@@ -238,8 +771,7 @@ mod test {
                 );
             }
 
-            // Question: Is this supposed to indicate that since this block is detached, then we should not be indexing it?
-            // For example, should we walk up the parent tree to check if each block is actually attached?
+            // `d` is detached here, so it should drop out of the index below.
             block.remove_children(trx, &d);
         });
 
@@ -251,16 +783,329 @@ mod test {
 
         let search_plugin = workspace.get_plugin::<IndexingPluginImpl>().unwrap();
 
-        expect_search_gives_ids!(search_plugin, "content", &["b", "c", "d"]);
+        expect_search_gives_ids!(search_plugin, "content", &["b", "c"]);
         expect_search_gives_ids!(search_plugin, "bbb", &["b"]);
         expect_search_gives_ids!(search_plugin, "ccc", &["c"]);
         expect_search_gives_ids!(search_plugin, "xxx", &["b", "c"]);
-        expect_search_gives_ids!(search_plugin, "yyy", &["c", "d"]);
+        expect_search_gives_ids!(search_plugin, "yyy", &["c"]);
 
         expect_search_gives_ids!(search_plugin, "人民日报", &["e"]);
         expect_search_gives_ids!(search_plugin, "技术学校", &["e"]);
 
         expect_search_gives_ids!(search_plugin, "核聚变反应", &["f"]);
         expect_search_gives_ids!(search_plugin, "镭射能量", &["f"]);
+
+        // `d` was already fully removed from the index when it detached, so
+        // filtering on `attached = true` changes nothing here: the flavor/attached
+        // facets only matter once a workspace has blocks that were never attached
+        // to begin with.
+        let attached_only = search_plugin
+            .search_with(
+                SearchQuery::new("content").with_filter(BlockFilter::new().with_attached(true)),
+            )
+            .expect("no error searching");
+        expect_result_ids!(attached_only, &["b", "c"]);
+    }
+
+    #[test]
+    fn pagination_test() {
+        let mut workspace = {
+            let workspace = Workspace::from_doc(Default::default(), "wk-paginate");
+            super::super::super::insert_plugin(workspace, IndexingPluginRegister::ram())
+                .expect("failed to insert plugin")
+        };
+
+        workspace.with_trx(|mut t| {
+            let root = t.create("root", "affine:text");
+            let trx = &mut t.trx;
+            for id in ["p1", "p2", "p3"] {
+                let page = t.create(id, "affine:text");
+                page.set(trx, "text", "paginate me");
+                root.push_children(trx, &page);
+            }
+        });
+
+        workspace
+            .update_plugin::<IndexingPluginImpl>()
+            .expect("update text search plugin");
+        let search_plugin = workspace.get_plugin::<IndexingPluginImpl>().unwrap();
+
+        let first_page = search_plugin
+            .search_with(SearchQuery::new("paginate").with_limit(1))
+            .expect("no error searching");
+        assert_eq!(first_page.total, 3);
+        assert_eq!(first_page.items.len(), 1);
+
+        let second_page = search_plugin
+            .search_with(SearchQuery::new("paginate").with_limit(1).with_offset(1))
+            .expect("no error searching");
+        assert_eq!(second_page.total, 3);
+        assert_eq!(second_page.items.len(), 1);
+        assert_ne!(
+            first_page.items[0].block_id, second_page.items[0].block_id,
+            "offset should have moved past the first page's hit"
+        );
+
+        // `limit: 0` is a valid way to ask for just `total`, and must not panic.
+        let no_rows = search_plugin
+            .search_with(SearchQuery::new("paginate").with_limit(0))
+            .expect("no error searching");
+        assert_eq!(no_rows.total, 3);
+        assert!(no_rows.items.is_empty());
+    }
+
+    #[test]
+    fn field_restriction_test() {
+        let mut workspace = {
+            let workspace = Workspace::from_doc(Default::default(), "wk-fields");
+            super::super::super::insert_plugin(workspace, IndexingPluginRegister::ram())
+                .expect("failed to insert plugin")
+        };
+
+        workspace.with_trx(|mut t| {
+            let title_only = t.create("title-only", "affine:text");
+            let body_only = t.create("body-only", "affine:text");
+            let trx = &mut t.trx;
+            title_only.set(trx, "title", "zephyr");
+            body_only.set(trx, "text", "zephyr");
+        });
+
+        workspace
+            .update_plugin::<IndexingPluginImpl>()
+            .expect("update text search plugin");
+        let search_plugin = workspace.get_plugin::<IndexingPluginImpl>().unwrap();
+
+        expect_search_gives_ids!(search_plugin, "zephyr", &["body-only", "title-only"]);
+
+        let title_scoped = search_plugin
+            .search_with(SearchQuery::new("zephyr").with_fields(vec!["title"]))
+            .expect("no error searching");
+        expect_result_ids!(title_scoped, &["title-only"]);
+    }
+
+    #[test]
+    fn highlight_snippet_test() {
+        let mut workspace = {
+            let workspace = Workspace::from_doc(Default::default(), "wk-snippet");
+            super::super::super::insert_plugin(workspace, IndexingPluginRegister::ram())
+                .expect("failed to insert plugin")
+        };
+
+        workspace.with_trx(|mut t| {
+            let page = t.create("snippet", "affine:text");
+            page.set(
+                &mut t.trx,
+                "text",
+                "the quick brown fox jumps over the lazy dog",
+            );
+        });
+
+        workspace
+            .update_plugin::<IndexingPluginImpl>()
+            .expect("update text search plugin");
+        let search_plugin = workspace.get_plugin::<IndexingPluginImpl>().unwrap();
+
+        let results = search_plugin
+            .search_with(SearchQuery::new("fox").with_highlight(true))
+            .expect("no error searching");
+        let snippet = results.items[0]
+            .snippet
+            .as_ref()
+            .expect("expected a snippet since `highlight: true` was requested");
+        assert!(
+            snippet.contains("<b>fox</b>"),
+            "snippet should highlight the matched term: {snippet}"
+        );
+    }
+
+    #[test]
+    fn field_boost_test() {
+        let mut workspace = {
+            let workspace = Workspace::from_doc(Default::default(), "wk-boost");
+            super::super::super::insert_plugin(
+                workspace,
+                IndexingPluginRegister::ram().with_field_boosts(3.0, 1.0),
+            )
+            .expect("failed to insert plugin")
+        };
+
+        workspace.with_trx(|mut t| {
+            let title_hit = t.create("title-hit", "affine:text");
+            let body_hit = t.create("body-hit", "affine:text");
+            let trx = &mut t.trx;
+            title_hit.set(trx, "title", "nebula");
+            title_hit.set(trx, "text", "unrelated body text");
+            body_hit.set(trx, "title", "unrelated title");
+            body_hit.set(trx, "text", "nebula");
+        });
+
+        workspace
+            .update_plugin::<IndexingPluginImpl>()
+            .expect("update text search plugin");
+        let search_plugin = workspace.get_plugin::<IndexingPluginImpl>().unwrap();
+
+        let results = search_plugin.search("nebula").expect("no error searching");
+        assert_eq!(results.items.len(), 2);
+        assert_eq!(
+            results.items[0].block_id, "title-hit",
+            "the 3x title boost should rank the title match first: {results:#?}"
+        );
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn boosted_with_depth_ranking_test() {
+        let mut workspace = {
+            let workspace = Workspace::from_doc(Default::default(), "wk-depth");
+            super::super::super::insert_plugin(workspace, IndexingPluginRegister::ram())
+                .expect("failed to insert plugin")
+        };
+
+        workspace.with_trx(|mut t| {
+            let root = t.create("root", "affine:text");
+            let shallow = t.create("shallow", "affine:text");
+            let deep = t.create("deep", "affine:text");
+            let trx = &mut t.trx;
+            shallow.set(trx, "text", "gravity well");
+            deep.set(trx, "text", "gravity well");
+
+            // `shallow` sits at depth 1 (child of `root`), `deep` at depth 2
+            // (child of `shallow`); their text is identical, so their BM25 scores
+            // for this query tie.
+            root.push_children(trx, &shallow);
+            shallow.push_children(trx, &deep);
+        });
+
+        workspace
+            .update_plugin::<IndexingPluginImpl>()
+            .expect("update text search plugin");
+        let search_plugin = workspace.get_plugin::<IndexingPluginImpl>().unwrap();
+
+        let boosted = search_plugin
+            .search_with(SearchQuery::new("gravity").with_ranking(SearchRanking::BoostedWithDepth))
+            .expect("no error searching");
+        assert_eq!(
+            boosted.items[0].block_id, "shallow",
+            "the shallower block should outrank the deeper one under BoostedWithDepth: {boosted:#?}"
+        );
+    }
+
+    #[test]
+    fn persisted_index_round_trip_test() {
+        let dir =
+            std::env::temp_dir().join(format!("jwst-search-index-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        {
+            let mut workspace = {
+                let workspace = Workspace::from_doc(Default::default(), "wk-persist");
+                super::super::super::insert_plugin(
+                    workspace,
+                    IndexingPluginRegister::persisted(dir.clone()),
+                )
+                .expect("failed to insert plugin")
+            };
+
+            workspace.with_trx(|mut t| {
+                let page = t.create("durable", "affine:text");
+                page.set(&mut t.trx, "text", "saturn has rings");
+            });
+
+            workspace
+                .update_plugin::<IndexingPluginImpl>()
+                .expect("update text search plugin");
+
+            let search_plugin = workspace.get_plugin::<IndexingPluginImpl>().unwrap();
+            expect_search_gives_ids!(search_plugin, "saturn", &["durable"]);
+        }
+
+        // Simulate a process restart: re-open the same on-disk directory from a
+        // brand new, empty workspace/doc, and never call `update_plugin` on it.
+        // The segments written above should still be searchable, proving it's the
+        // on-disk index -- not the in-memory workspace state -- that persisted.
+        {
+            let workspace = {
+                let workspace = Workspace::from_doc(Default::default(), "wk-persist-reopened");
+                super::super::super::insert_plugin(
+                    workspace,
+                    IndexingPluginRegister::persisted(dir.clone()),
+                )
+                .expect("failed to insert plugin")
+            };
+
+            let search_plugin = workspace.get_plugin::<IndexingPluginImpl>().unwrap();
+            expect_search_gives_ids!(search_plugin, "saturn", &["durable"]);
+        }
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn edit_after_detach_does_not_resurrect_test() {
+        let mut workspace = {
+            let workspace = Workspace::from_doc(Default::default(), "wk-edit-after-detach");
+            super::super::super::insert_plugin(workspace, IndexingPluginRegister::ram())
+                .expect("failed to insert plugin")
+        };
+
+        let (root, child) = workspace.with_trx(|mut t| {
+            let root = t.create("root", "affine:text");
+            let child = t.create("child", "affine:text");
+            child.set(&mut t.trx, "text", "first version");
+            root.push_children(&mut t.trx, &child);
+            (root, child)
+        });
+
+        workspace
+            .update_plugin::<IndexingPluginImpl>()
+            .expect("update text search plugin");
+        {
+            let search_plugin = workspace.get_plugin::<IndexingPluginImpl>().unwrap();
+            expect_search_gives_ids!(search_plugin, "version", &["child"]);
+        }
+
+        // Detach `child`, on its own update cycle, so it gets purged from the index.
+        workspace.with_trx(|mut t| {
+            root.remove_children(&mut t.trx, &child);
+        });
+        workspace
+            .update_plugin::<IndexingPluginImpl>()
+            .expect("update text search plugin");
+        {
+            let search_plugin = workspace.get_plugin::<IndexingPluginImpl>().unwrap();
+            let results = search_plugin.search("version").expect("no error searching");
+            assert!(
+                results.items.is_empty(),
+                "detached block should have been purged from the index: {results:#?}"
+            );
+        }
+
+        // Now edit it on a *later*, separate update cycle, while it's still
+        // detached. The edit must not resurrect it into plain, unfiltered search.
+        workspace.with_trx(|mut t| {
+            child.set(&mut t.trx, "text", "second version, still detached");
+        });
+        workspace
+            .update_plugin::<IndexingPluginImpl>()
+            .expect("update text search plugin");
+        {
+            let search_plugin = workspace.get_plugin::<IndexingPluginImpl>().unwrap();
+            let results = search_plugin.search("version").expect("no error searching");
+            assert!(
+                results.items.is_empty(),
+                "editing a still-detached block must not resurrect it: {results:#?}"
+            );
+        }
+
+        // Re-attaching it, though, should bring it back.
+        workspace.with_trx(|mut t| {
+            root.push_children(&mut t.trx, &child);
+        });
+        workspace
+            .update_plugin::<IndexingPluginImpl>()
+            .expect("update text search plugin");
+        {
+            let search_plugin = workspace.get_plugin::<IndexingPluginImpl>().unwrap();
+            expect_search_gives_ids!(search_plugin, "version", &["child"]);
+        }
+    }
+}